@@ -0,0 +1,379 @@
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread::JoinHandle;
+
+use anyhow::{Context, Result, bail};
+use flate2::Compression;
+use flate2::read::{GzDecoder, MultiGzDecoder};
+use flate2::write::GzEncoder;
+use tracing::{debug, info, warn};
+
+use crate::logging::PROGRESS;
+
+// zip 输入：每个文件条目当作一个虚拟文件走目录模式的管线。
+// 规划阶段只读中央目录与每个条目解压后的前 8KB（二进制嗅探、shebang），正文在处理该条目时才解压
+
+const ENTRY_HEAD_BYTES: u64 = 8 * 1024;
+
+#[derive(Debug)]
+pub struct ArchiveEntry {
+    pub archive: Arc<PathBuf>, // 所属 zip 文件
+    pub name: String,          // zip 内的原始条目名，读取时按它查找
+    pub rel_path: PathBuf,     // 规范化后的相对路径，用于输出镜像与 manifest
+    pub size: u64,             // 中央目录记录的解压后大小
+    pub crc32: u32,
+    pub head: Vec<u8>, // 解压后的开头（最多 8KB）
+}
+
+pub fn is_zip_path(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("zip"))
+}
+
+// 列出全部文件条目（目录条目跳过）。任一条目加密即报错：无法解密，也不应静默漏掉；
+// 绝对路径、含 `..` 等不安全的条目名告警后跳过，避免输出写到输出根目录之外
+pub fn list_zip_entries(path: &Path) -> Result<Vec<ArchiveEntry>> {
+    let archive_path = Arc::new(path.to_path_buf());
+    let file = File::open(path).with_context(|| format!("打开压缩包失败: {}", path.display()))?;
+    let mut zip = zip::ZipArchive::new(file).with_context(|| format!("解析 zip 失败: {}", path.display()))?;
+    let mut entries = Vec::new();
+    for idx in 0..zip.len() {
+        let (name, size, crc32) = {
+            let raw = zip
+                .by_index_raw(idx)
+                .with_context(|| format!("读取 zip 条目 #{} 失败: {}", idx, path.display()))?;
+            if raw.encrypted() {
+                bail!("不支持加密的 zip: {}（条目 {}）", path.display(), raw.name());
+            }
+            if raw.is_dir() {
+                continue;
+            }
+            (raw.name().to_string(), raw.size(), raw.crc32())
+        };
+        let Some(rel_path) = safe_rel_path(&name) else {
+            warn!("zip 条目路径不安全，跳过: {}", name);
+            continue;
+        };
+        let mut head = Vec::new();
+        zip.by_index(idx)
+            .and_then(|entry| Ok(entry.take(ENTRY_HEAD_BYTES).read_to_end(&mut head)?))
+            .with_context(|| format!("解压 zip 条目失败: {}", name))?;
+        entries.push(ArchiveEntry { archive: Arc::clone(&archive_path), name, rel_path, size, crc32, head });
+    }
+    Ok(entries)
+}
+
+// 读取条目全文。实际解压字节数超过中央目录记录的大小时报错，防止伪造大小的压缩炸弹绕过大小阈值
+pub fn read_zip_entry(entry: &ArchiveEntry) -> Result<Vec<u8>> {
+    let file = File::open(entry.archive.as_path())
+        .with_context(|| format!("打开压缩包失败: {}", entry.archive.display()))?;
+    let mut zip = zip::ZipArchive::new(file).with_context(|| format!("解析 zip 失败: {}", entry.archive.display()))?;
+    let reader = zip.by_name(&entry.name).with_context(|| format!("zip 中找不到条目: {}", entry.name))?;
+    let mut bytes = Vec::with_capacity(entry.size as usize);
+    reader
+        .take(entry.size + 1)
+        .read_to_end(&mut bytes)
+        .with_context(|| format!("解压 zip 条目失败: {}", entry.name))?;
+    if bytes.len() as u64 > entry.size {
+        bail!("zip 条目 {} 解压后超过声明的 {} 字节，已放弃", entry.name, entry.size);
+    }
+    Ok(bytes)
+}
+
+// .gz 源文件：处理时整体解压，语言检测、输出文件名都按去掉 `.gz` 的逻辑名（app.log.gz → app.log）。
+// 支持多成员拼接的 gzip（logrotate 追加压缩的常见形态）
+
+// 未设置 --skip-large-file-size-mb 时解压产物的上限
+pub const GZIP_MAX_DECOMPRESSED_BYTES: u64 = 64 * 1024 * 1024;
+
+pub fn is_gzip_path(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("gz"))
+}
+
+// 去掉 `.gz` 后的逻辑路径；非 .gz 原样返回
+pub fn gzip_logical_path(path: &Path) -> PathBuf {
+    if is_gzip_path(path) { path.with_extension("") } else { path.to_path_buf() }
+}
+
+// 整体解压，超过 limit 字节即放弃（防止压缩炸弹撑爆内存）
+pub fn gunzip(compressed: &[u8], limit: u64) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    MultiGzDecoder::new(compressed)
+        .take(limit + 1)
+        .read_to_end(&mut bytes)
+        .context("gzip 数据无效或已损坏")?;
+    if bytes.len() as u64 > limit {
+        bail!("gzip 解压后超过 {} 字节，已放弃", limit);
+    }
+    Ok(bytes)
+}
+
+// 从压缩数据的开头解出最多 8KB，用于二进制嗅探；前缀被截断时返回已解出的部分，
+// 连头部都无法解析时返回 None（交给处理阶段报错）
+pub fn gunzip_head(compressed: &[u8]) -> Option<Vec<u8>> {
+    let mut decoder = MultiGzDecoder::new(compressed).take(ENTRY_HEAD_BYTES);
+    let mut head = Vec::new();
+    let mut buf = [0u8; 4096];
+    loop {
+        match decoder.read(&mut buf) {
+            Ok(0) => return Some(head),
+            Ok(n) => head.extend_from_slice(&buf[..n]),
+            Err(_) if !head.is_empty() => return Some(head),
+            Err(_) => return None,
+        }
+    }
+}
+
+// 只接受由普通分量组成的相对路径（`.` 忽略），与文件清单模式的路径约束一致
+fn safe_rel_path(name: &str) -> Option<PathBuf> {
+    let mut rel = PathBuf::new();
+    for component in Path::new(name).components() {
+        match component {
+            Component::Normal(part) => rel.push(part),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    (!rel.as_os_str().is_empty()).then_some(rel)
+}
+
+// --archive-output：摘要写入单个 zip / tar.gz 归档。
+// worker 照常把摘要写到输出目录（分块汇总、--merge-output 仍按散文件读取），完成后把路径交给写线程；
+// 写线程独占归档，按完成顺序追加到临时归档。结束时并入旧归档中本次未重写的条目（续跑、增量时保留此前的结果），
+// 重命名为目标文件，再删除已归档的散文件。任何写入失败都放弃归档、保留散文件
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    Zip,
+    TarGz,
+}
+
+impl ArchiveFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_string_lossy().to_ascii_lowercase();
+        if name.ends_with(".zip") {
+            Some(ArchiveFormat::Zip)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(ArchiveFormat::TarGz)
+        } else {
+            None
+        }
+    }
+}
+
+enum ArchiveSink {
+    Zip(zip::ZipWriter<File>),
+    TarGz(tar::Builder<GzEncoder<File>>),
+}
+
+impl ArchiveSink {
+    fn create(format: ArchiveFormat, path: &Path) -> Result<Self> {
+        let file = File::create(path).with_context(|| format!("创建归档失败: {}", path.display()))?;
+        Ok(match format {
+            ArchiveFormat::Zip => ArchiveSink::Zip(zip::ZipWriter::new(file)),
+            ArchiveFormat::TarGz => ArchiveSink::TarGz(tar::Builder::new(GzEncoder::new(file, Compression::default()))),
+        })
+    }
+
+    fn append(&mut self, name: &str, bytes: &[u8]) -> Result<()> {
+        match self {
+            ArchiveSink::Zip(zip) => {
+                let options = zip::write::SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+                zip.start_file(name, options)?;
+                zip.write_all(bytes)?;
+            }
+            ArchiveSink::TarGz(tar) => {
+                let mtime = std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .map(|d| d.as_secs())
+                    .unwrap_or(0);
+                let mut header = tar::Header::new_gnu();
+                header.set_size(bytes.len() as u64);
+                header.set_mode(0o644);
+                header.set_mtime(mtime);
+                tar.append_data(&mut header, name, bytes)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<()> {
+        match self {
+            ArchiveSink::Zip(zip) => {
+                zip.finish()?.sync_all()?;
+            }
+            ArchiveSink::TarGz(tar) => {
+                tar.into_inner()?.finish()?.sync_all()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// 读取旧归档中未被本次重写的条目。读取中途出错视为归档损坏：返回已读出的部分与错误，由调用方决定回退
+fn read_previous(format: ArchiveFormat, path: &Path, skip: &HashSet<String>) -> (Vec<(String, Vec<u8>)>, Option<anyhow::Error>) {
+    let mut kept = Vec::new();
+    let result = (|| -> Result<()> {
+        let file = File::open(path)?;
+        match format {
+            ArchiveFormat::Zip => {
+                let mut zip = zip::ZipArchive::new(file)?;
+                for idx in 0..zip.len() {
+                    let mut entry = zip.by_index(idx)?;
+                    if entry.is_dir() || skip.contains(entry.name()) {
+                        continue;
+                    }
+                    let mut bytes = Vec::new();
+                    entry.read_to_end(&mut bytes)?;
+                    kept.push((entry.name().to_string(), bytes));
+                }
+            }
+            ArchiveFormat::TarGz => {
+                let mut tar = tar::Archive::new(GzDecoder::new(file));
+                for entry in tar.entries()? {
+                    let mut entry = entry?;
+                    let name = entry.path()?.to_string_lossy().into_owned();
+                    if !entry.header().entry_type().is_file() || skip.contains(&name) {
+                        continue;
+                    }
+                    let mut bytes = Vec::new();
+                    entry.read_to_end(&mut bytes)?;
+                    kept.push((name, bytes));
+                }
+            }
+        }
+        Ok(())
+    })();
+    (kept, result.err())
+}
+
+type ArchiveJob = (String, PathBuf);
+type WriterOutcome = Result<(ArchiveSink, Vec<ArchiveJob>)>;
+
+pub struct ArchiveWriter {
+    dest: PathBuf,
+    tmp_path: PathBuf,
+    format: ArchiveFormat,
+    output_root: PathBuf,
+    tx: Mutex<Option<mpsc::Sender<ArchiveJob>>>,
+    handle: Mutex<Option<JoinHandle<WriterOutcome>>>,
+}
+
+impl ArchiveWriter {
+    // 在目标文件同目录创建临时归档并启动写线程；目标文件在 finish 时才被替换，中途崩溃不影响旧归档
+    pub fn start(dest: &Path, output_root: &Path) -> Result<Self> {
+        let Some(format) = ArchiveFormat::from_path(dest) else {
+            bail!("--archive-output 只支持 .zip、.tar.gz、.tgz: {}", dest.display());
+        };
+        if let Some(parent) = dest.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent).with_context(|| format!("创建归档所在目录失败: {}", parent.display()))?;
+        }
+        let file_name = dest.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        let tmp_path = dest.with_file_name(format!(".{}.tmp-{}", file_name, std::process::id()));
+        let mut sink = ArchiveSink::create(format, &tmp_path)?;
+        let (tx, rx) = mpsc::channel::<ArchiveJob>();
+        let handle = std::thread::Builder::new()
+            .name("archive-writer".to_string())
+            .spawn(move || -> WriterOutcome {
+                let mut written = Vec::new();
+                for (name, path) in rx {
+                    let bytes = std::fs::read(&path).with_context(|| format!("读取摘要失败: {}", path.display()))?;
+                    sink.append(&name, &bytes).with_context(|| format!("写入归档条目失败: {}", name))?;
+                    written.push((name, path));
+                }
+                Ok((sink, written))
+            })
+            .context("启动归档写线程失败")?;
+        Ok(ArchiveWriter {
+            dest: dest.to_path_buf(),
+            tmp_path,
+            format,
+            output_root: output_root.to_path_buf(),
+            tx: Mutex::new(Some(tx)),
+            handle: Mutex::new(Some(handle)),
+        })
+    }
+
+    // 条目名为摘要相对输出根目录的路径（统一用 `/` 分隔）。写线程已因错误退出时发送失败，忽略即可：结束时统一回退
+    pub fn add(&self, summary_path: &Path) {
+        let rel = summary_path.strip_prefix(&self.output_root).unwrap_or(summary_path);
+        let name = rel
+            .components()
+            .filter_map(|c| match c {
+                Component::Normal(part) => Some(part.to_string_lossy()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+        if let Some(tx) = self.tx.lock().unwrap_or_else(|e| e.into_inner()).as_ref() {
+            let _ = tx.send((name, summary_path.to_path_buf()));
+        }
+    }
+
+    // 关闭 channel、等待写线程，合并旧归档后替换目标文件并删除已归档的散文件。
+    // 失败时删除临时归档、保留散文件与旧归档，只告警不中止（摘要本身已经生成）
+    pub fn finish(&self) {
+        drop(self.tx.lock().unwrap_or_else(|e| e.into_inner()).take());
+        let Some(handle) = self.handle.lock().unwrap_or_else(|e| e.into_inner()).take() else {
+            return;
+        };
+        let outcome = handle.join().unwrap_or_else(|_| Err(anyhow::anyhow!("归档写线程异常退出")));
+        match outcome.and_then(|(sink, written)| self.seal(sink, &written).map(|()| written)) {
+            Ok(written) => {
+                info!(
+                    target: PROGRESS, event = "archive", path = %self.dest.display(), files = written.len(),
+                    "摘要已写入归档: {}（本次 {} 个）", self.dest.display(), written.len()
+                );
+                self.remove_loose(&written);
+            }
+            Err(err) => {
+                let _ = std::fs::remove_file(&self.tmp_path);
+                warn!("写入归档 {} 失败，摘要保留为散文件（输出目录中）: {:#}", self.dest.display(), err);
+            }
+        }
+    }
+
+    fn seal(&self, mut sink: ArchiveSink, written: &[ArchiveJob]) -> Result<()> {
+        if self.dest.exists() {
+            let names: HashSet<String> = written.iter().map(|(name, _)| name.clone()).collect();
+            let (kept, err) = read_previous(self.format, &self.dest, &names);
+            if let Some(err) = err {
+                // 旧归档损坏：保留可读出的条目，原文件另存一份以便排查，不被新归档直接覆盖
+                let mut backup = self.dest.clone().into_os_string();
+                backup.push(".corrupt");
+                let backup = PathBuf::from(backup);
+                warn!(
+                    "已有归档 {} 读取失败（{:#}），保留其中可读取的 {} 个条目，原文件另存为 {}",
+                    self.dest.display(), err, kept.len(), backup.display()
+                );
+                std::fs::rename(&self.dest, &backup).with_context(|| format!("备份损坏的归档失败: {}", backup.display()))?;
+            } else {
+                debug!("并入已有归档中的 {} 个条目", kept.len());
+            }
+            for (name, bytes) in kept {
+                sink.append(&name, &bytes).with_context(|| format!("写入归档条目失败: {}", name))?;
+            }
+        }
+        sink.finish().context("写入归档失败")?;
+        std::fs::rename(&self.tmp_path, &self.dest).with_context(|| format!("重命名归档失败: {}", self.dest.display()))
+    }
+
+    // 删除已归档的散文件，并自下而上清理因此变空的镜像目录（不越过输出根目录）
+    fn remove_loose(&self, written: &[ArchiveJob]) {
+        for (_, path) in written {
+            if let Err(err) = std::fs::remove_file(path) {
+                debug!("删除已归档的摘要失败 {}: {}", path.display(), err);
+                continue;
+            }
+            let mut dir = path.parent();
+            while let Some(d) = dir {
+                if d == self.output_root || !d.starts_with(&self.output_root) || std::fs::remove_dir(d).is_err() {
+                    break;
+                }
+                dir = d.parent();
+            }
+        }
+    }
+}
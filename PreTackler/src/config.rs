@@ -0,0 +1,149 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::logging::LogFormat;
+use crate::processor::{EmptyFileMode, JobOrder, OnCollision, OutputFormat, RetryClass};
+use crate::provider::ProviderKind;
+
+// TOML 配置文件（--config）：键名与命令行长参数一致，全部可选
+// 优先级：命令行/环境变量 > 配置文件 > 默认值
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct FileConfig {
+    pub input: Option<PathBuf>,
+    pub version: Option<String>,
+    pub prompt: Option<PathBuf>,
+    pub system_prompt: Option<PathBuf>,
+    pub task_prompt: Option<PathBuf>,
+    pub model: Option<String>,
+    pub temperature: Option<f32>,
+    pub top_k: Option<u32>,
+    pub concurrency_ceil: Option<usize>,
+    pub unbounded: Option<bool>,
+    pub rate_limit_rps: Option<f64>,
+    pub rate_limit_bytes_per_sec: Option<u64>,
+    pub rate_limit_upload_bps: Option<u64>,
+    pub rate_limit_download_bps: Option<u64>,
+    #[serde(rename = "connect-timeout")]
+    pub connect_timeout_secs: Option<u64>,
+    pub pool_max_idle_per_host: Option<usize>,
+    pub pool_idle_timeout_secs: Option<u64>,
+    pub http2_prior_knowledge: Option<bool>,
+    #[serde(rename = "request-timeout")]
+    pub request_timeout_secs: Option<u64>,
+    #[serde(rename = "stream-idle-timeout")]
+    pub stream_idle_timeout_secs: Option<u64>,
+    pub skip_large_file_size_mb: Option<u64>,
+    #[serde(rename = "skip-ext")]
+    pub skip_exts: Option<Vec<String>>,
+    pub verbose: Option<bool>,
+    pub inject_fault: Option<String>,
+    pub long_file_bytes_threshold: Option<u64>,
+    pub long_file_lines_threshold: Option<u64>,
+    pub long_channel_enabled: Option<bool>,
+    pub long_channel_timeout_multiplier: Option<f32>,
+    #[serde(rename = "long-channel-request-timeout")]
+    pub long_channel_request_timeout_secs: Option<u64>,
+    #[serde(rename = "long-channel-idle-timeout")]
+    pub long_channel_idle_timeout_secs: Option<u64>,
+    pub long_channel_adaptive_idle_enabled: Option<bool>,
+    pub skip_existing: Option<bool>,
+    pub api_base: Option<String>,
+    pub provider: Option<ProviderKind>,
+    pub no_ignore: Option<bool>,
+    pub skip_binary: Option<bool>,
+    pub always_base64: Option<bool>,
+    pub max_tokens: Option<u32>,
+    pub long_channel_max_tokens: Option<u32>,
+    pub top_p: Option<f64>,
+    pub frequency_penalty: Option<f64>,
+    pub presence_penalty: Option<f64>,
+    pub restart: Option<bool>,
+    pub chunk_bytes: Option<u64>,
+    pub no_stream: Option<bool>,
+    pub output_template: Option<String>,
+    pub output_dir: Option<PathBuf>,
+    pub rate_limit_burst: Option<u32>,
+    pub total_byte_budget: Option<u64>,
+    pub estimate_only: Option<bool>,
+    pub dry_run: Option<bool>,
+    pub confirm: Option<bool>,
+    pub proxy: Option<String>,
+    pub ca_cert: Option<PathBuf>,
+    pub danger_accept_invalid_certs: Option<bool>,
+    pub retry_budget: Option<usize>,
+    pub breaker_threshold: Option<f64>,
+    pub breaker_window: Option<usize>,
+    #[serde(rename = "breaker-cooldown")]
+    pub breaker_cooldown_secs: Option<u64>,
+    pub assumed_throughput_bps: Option<u64>,
+    pub scale_normal_timeout: Option<bool>,
+    pub merge_output: Option<PathBuf>,
+    pub front_matter: Option<bool>,
+    pub incremental: Option<bool>,
+    pub hash_skip: Option<bool>,
+    pub hash_prune: Option<bool>,
+    pub stdin_name: Option<String>,
+    pub file_list: Option<PathBuf>,
+    pub format: Option<OutputFormat>,
+    #[serde(rename = "priority-glob")]
+    pub priority_globs: Option<Vec<String>>,
+    pub retry_failed: Option<PathBuf>,
+    pub git_diff: Option<String>,
+    pub log_file: Option<PathBuf>,
+    pub log_format: Option<LogFormat>,
+    pub timestamp_format: Option<String>,
+    pub stats_json: Option<PathBuf>,
+    pub max_retries: Option<usize>,
+    pub max_retry_wait_secs: Option<u64>,
+    pub retry_on: Option<Vec<RetryClass>>,
+    pub skip_empty: Option<EmptyFileMode>,
+    pub prompt_dir: Option<PathBuf>,
+    pub input_encoding: Option<String>,
+    pub normalize_newlines: Option<bool>,
+    pub include_reasoning: Option<bool>,
+    pub group_by_dir: Option<bool>,
+    #[serde(rename = "include")]
+    pub includes: Option<Vec<String>>,
+    pub follow_symlinks: Option<bool>,
+    pub allow_symlink_escape: Option<bool>,
+    pub max_depth: Option<usize>,
+    pub preserve_path: Option<bool>,
+    pub json_mode: Option<bool>,
+    pub stop: Option<Vec<String>>,
+    pub on_collision: Option<OnCollision>,
+    pub order: Option<JobOrder>,
+    pub api_key_command: Option<String>,
+    pub no_bandwidth_probe: Option<bool>,
+    pub per_task_memory_mb: Option<u64>,
+    pub per_task_bandwidth_kbps: Option<u64>,
+    pub metrics_file: Option<PathBuf>,
+    pub archive_output: Option<PathBuf>,
+    pub redact: Option<bool>,
+    #[serde(rename = "redact-pattern")]
+    pub redact_patterns: Option<Vec<String>>,
+    #[serde(rename = "header")]
+    pub headers: Option<Vec<String>>,
+    pub allow_authorization_header: Option<bool>,
+    pub keep_partial_on_failure: Option<bool>,
+    pub append: Option<bool>,
+    #[serde(rename = "first-byte-timeout")]
+    pub first_byte_timeout_secs: Option<u64>,
+    pub long_channel_first_byte_multiplier: Option<f32>,
+    pub max_payload_bytes: Option<u64>,
+    pub temp_dir: Option<PathBuf>,
+    pub examples: Option<PathBuf>,
+    #[serde(rename = "context-file")]
+    pub context_files: Option<Vec<PathBuf>>,
+    pub context_max_bytes: Option<usize>,
+    pub lang_map: Option<BTreeMap<String, String>>,
+}
+
+pub fn load_file_config(path: &Path) -> Result<FileConfig> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("读取配置文件失败: {}", path.display()))?;
+    toml::from_str(&text).with_context(|| format!("解析配置文件失败: {}", path.display()))
+}
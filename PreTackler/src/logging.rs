@@ -0,0 +1,290 @@
+use std::fmt;
+use std::io::{self, IsTerminal, Write};
+use std::path::Path;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use chrono::format::StrftimeItems;
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressState, ProgressStyle};
+use serde::Deserialize;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::Layer;
+use tracing_subscriber::filter::{LevelFilter, Targets};
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+
+// 进度事件（开始/完成/失败/计划/中断）使用的 target：始终输出到 stdout，不受 --verbose 与 --log-file 影响
+pub const PROGRESS: &str = "progress";
+// 逐文件的开始/完成行：显示进度条时由进度条代替，不再逐行输出
+pub const PROGRESS_PER_FILE: &str = "progress::file";
+
+// 进度条：非 verbose、text 格式且 stdout 是终端时启用，init 时确定
+static BARS: OnceLock<MultiProgress> = OnceLock::new();
+
+// 日志行格式：text 为 `HH:MM:SS [warn] 消息`；json 为每行一个对象（供 CI 采集入库）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl std::str::FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            other => Err(format!("未知日志格式: {}（可选 text、json）", other)),
+        }
+    }
+}
+
+// 诊断日志：默认 info 级别，--verbose 等价于 debug；默认写 stderr，--log-file 时追加写入文件。
+// 进度事件（target = PROGRESS）单独一层写 stdout；依赖库（reqwest/hyper 等）只输出 warn 及以上
+pub fn init(verbose: bool, format: LogFormat, timestamp_format: &str, log_file: Option<&Path>) -> Result<()> {
+    let _ = TIMESTAMP_FORMAT.set(timestamp_format.to_string());
+    let level = if verbose { Level::DEBUG } else { Level::INFO };
+    let diag_filter = Targets::new()
+        .with_target(PROGRESS, LevelFilter::OFF)
+        .with_target(env!("CARGO_CRATE_NAME"), level)
+        .with_default(Level::WARN);
+    // verbose 或管道/CI（非终端）时回落为逐行日志
+    let bars = !verbose && format == LogFormat::Text && std::io::stdout().is_terminal();
+    if bars {
+        let _ = BARS.set(MultiProgress::with_draw_target(ProgressDrawTarget::stdout()));
+    }
+    let mut progress_filter = Targets::new().with_target(PROGRESS, Level::INFO);
+    if bars {
+        progress_filter = progress_filter.with_target(PROGRESS_PER_FILE, LevelFilter::OFF);
+    }
+    let line = LineFormat { json: format == LogFormat::Json };
+    let progress = tracing_subscriber::fmt::layer()
+        .with_ansi(false)
+        .event_format(line)
+        .with_writer(|| SuspendBars(std::io::stdout()))
+        .with_filter(progress_filter);
+    let diag = tracing_subscriber::fmt::layer().with_ansi(false).event_format(line);
+    match log_file {
+        Some(path) => {
+            let file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .with_context(|| format!("打开日志文件失败: {}", path.display()))?;
+            tracing_subscriber::registry()
+                .with(progress)
+                .with(diag.with_writer(std::sync::Mutex::new(file)).with_filter(diag_filter))
+                .init();
+        }
+        None => tracing_subscriber::registry()
+            .with(progress)
+            .with(diag.with_writer(|| SuspendBars(std::io::stderr())).with_filter(diag_filter))
+            .init(),
+    }
+    Ok(())
+}
+
+// 进度条显示期间写日志：先擦掉进度条再写，写完重绘，避免日志行与进度条互相覆盖
+struct SuspendBars<W>(W);
+
+impl<W: Write> Write for SuspendBars<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match BARS.get() {
+            Some(multi) => multi.suspend(|| self.0.write(buf)),
+            None => self.0.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+// 目录模式的总进度条：normal / long 各一条（数量为 0 的通道不显示），未启用进度条时为空操作
+#[derive(Clone, Default)]
+pub struct ChannelBars {
+    normal: Option<ProgressBar>,
+    long: Option<ProgressBar>,
+}
+
+impl ChannelBars {
+    pub fn new(normal: usize, long: usize) -> Self {
+        Self::with_lengths((normal > 0).then_some(normal), (long > 0).then_some(long))
+    }
+
+    // 流式遍历：总数未知，从 0 开始随 discovered 增长；长通道关闭时不建 long 条
+    pub fn growing(long_enabled: bool) -> Self {
+        Self::with_lengths(Some(0), long_enabled.then_some(0))
+    }
+
+    fn with_lengths(normal: Option<usize>, long: Option<usize>) -> Self {
+        let Some(multi) = BARS.get() else {
+            return Self::default();
+        };
+        let style = ProgressStyle::with_template("{prefix:>6} [{elapsed_precise}] {bar:40} {pos}/{len} {rate} ETA {eta}")
+            .expect("进度条模板有效")
+            .with_key("rate", |state: &ProgressState, w: &mut dyn fmt::Write| {
+                let _ = write!(w, "{:.1} 个/s", state.per_sec());
+            })
+            .progress_chars("=> ");
+        let add = |len: Option<usize>, prefix: &'static str| {
+            len.map(|len| {
+                multi
+                    .add(ProgressBar::new(len as u64))
+                    .with_style(style.clone())
+                    .with_prefix(prefix)
+            })
+        };
+        Self { normal: add(normal, "normal"), long: add(long, "long") }
+    }
+
+    // 流式遍历中新发现一个待处理文件
+    pub fn discovered(&self, is_long: bool) {
+        let bar = if is_long { &self.long } else { &self.normal };
+        if let Some(bar) = bar {
+            bar.inc_length(1);
+        }
+    }
+
+    // 完成或失败都计为已处理
+    pub fn inc(&self, is_long: bool) {
+        let bar = if is_long { &self.long } else { &self.normal };
+        if let Some(bar) = bar {
+            bar.inc(1);
+        }
+    }
+
+    // 收起进度条，之后的汇总信息按普通文本输出
+    pub fn finish(&self) {
+        for bar in [&self.normal, &self.long].into_iter().flatten() {
+            bar.finish_and_clear();
+        }
+    }
+}
+
+// text：`HH:MM:SS [warn] 消息`，info 级别不加标签，结构化字段（event/path 等）不打印；
+// json：`{"ts": unix 秒, "level": "warn", "event": "retry", "path": ..., "msg": ...}`，事件字段原样展开
+#[derive(Clone, Copy)]
+struct LineFormat {
+    json: bool,
+}
+
+impl<S, N> FormatEvent<S, N> for LineFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(&self, _ctx: &FmtContext<'_, S, N>, mut writer: Writer<'_>, event: &Event<'_>) -> fmt::Result {
+        let level = *event.metadata().level();
+        if self.json {
+            let mut fields = JsonFields(vec![
+                ("ts", unix_ts().into()),
+                ("level", level.as_str().to_ascii_lowercase().into()),
+            ]);
+            event.record(&mut fields);
+            // 事件类型紧跟 level，便于肉眼扫读
+            if let Some(pos) = fields.0.iter().position(|(key, _)| *key == "event") {
+                fields.0[2..=pos].rotate_right(1);
+            }
+            write!(writer, "{{")?;
+            for (i, (key, value)) in fields.0.iter().enumerate() {
+                let sep = if i == 0 { "" } else { "," };
+                write!(writer, "{}{}:{}", sep, serde_json::Value::from(*key), value)?;
+            }
+            return writeln!(writer, "}}");
+        }
+        let tag = match level {
+            Level::ERROR => "[error] ",
+            Level::WARN => "[warn] ",
+            Level::INFO => "",
+            Level::DEBUG => "[debug] ",
+            Level::TRACE => "[trace] ",
+        };
+        let mut message = MessageOnly(String::new());
+        event.record(&mut message);
+        writeln!(writer, "{} {}{}", ts_now(), tag, message.0)
+    }
+}
+
+// text 格式只取 message 字段
+struct MessageOnly(String);
+
+impl Visit for MessageOnly {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.0 = format!("{:?}", value);
+        }
+    }
+}
+
+// json 格式按记录顺序收集全部字段（ts/level 在前）：数值保持数值，message 改名为 msg，其余按 Display/Debug 转为字符串
+struct JsonFields(Vec<(&'static str, serde_json::Value)>);
+
+impl JsonFields {
+    fn put(&mut self, field: &Field, value: serde_json::Value) {
+        let key = if field.name() == "message" { "msg" } else { field.name() };
+        self.0.push((key, value));
+    }
+}
+
+impl Visit for JsonFields {
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.put(field, value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.put(field, value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.put(field, value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.put(field, value.into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.put(field, value.into());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.put(field, format!("{:?}", value).into());
+    }
+}
+
+// 完整时间戳：unix 秒（毫秒精度），json 日志使用
+pub fn unix_ts() -> f64 {
+    use std::time::SystemTime;
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or(Duration::from_secs(0));
+    (now.as_millis() as f64) / 1000.0
+}
+
+pub const DEFAULT_TIMESTAMP_FORMAT: &str = "%H:%M:%S";
+
+// --timestamp-format 在 init 时设置一次；未初始化（如日志初始化之前）按默认格式
+static TIMESTAMP_FORMAT: OnceLock<String> = OnceLock::new();
+
+// 校验 strftime 格式串（配置文件中的值绕过了 clap 的 value_parser，也走这里）
+pub fn check_timestamp_format(format: &str) -> std::result::Result<(), String> {
+    StrftimeItems::new(format)
+        .parse()
+        .map(|_| ())
+        .map_err(|e| format!("无效的时间戳格式 {:?}: {}", format, e))
+}
+
+// 本地时区的当前时间，所有带时间前缀的输出统一调用
+pub fn ts_now() -> String {
+    let format = TIMESTAMP_FORMAT.get().map(String::as_str).unwrap_or(DEFAULT_TIMESTAMP_FORMAT);
+    chrono::Local::now().format(format).to_string()
+}
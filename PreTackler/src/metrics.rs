@@ -0,0 +1,86 @@
+use std::fmt::Write as _;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+// --metrics-file：运行期指标，worker 直接累加，按 Prometheus 文本格式（0.0.4）渲染。
+// 只实现用到的 counter 与直方图，不引入完整的 Prometheus 客户端
+
+// 每文件耗时直方图的桶上界（秒），另有隐含的 +Inf
+const DURATION_BUCKETS_SECS: [f64; 10] = [0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0, 300.0, 600.0];
+
+#[derive(Debug, Default)]
+pub struct Metrics {
+    requests: AtomicU64,   // HTTP 尝试次数（含重试）
+    retries: AtomicU64,
+    bytes_sent: AtomicU64, // 请求体字节数（含重试）
+    files_succeeded: AtomicU64,
+    files_failed: AtomicU64,
+    normal: Mutex<Histogram>,
+    long: Mutex<Histogram>,
+}
+
+#[derive(Debug, Default)]
+struct Histogram {
+    counts: [u64; DURATION_BUCKETS_SECS.len()], // 非累积：落在各桶的样本数
+    sum_secs: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn observe(&mut self, secs: f64) {
+        if let Some(idx) = DURATION_BUCKETS_SECS.iter().position(|&le| secs <= le) {
+            self.counts[idx] += 1;
+        }
+        self.sum_secs += secs;
+        self.count += 1;
+    }
+}
+
+impl Metrics {
+    pub fn add_bytes_sent(&self, bytes: u64) {
+        self.bytes_sent.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    // 每个文件结束（成功或失败）时调用一次
+    pub fn record_file(&self, long: bool, attempts: u64, retries: u64, elapsed: Duration, ok: bool) {
+        self.requests.fetch_add(attempts, Ordering::Relaxed);
+        self.retries.fetch_add(retries, Ordering::Relaxed);
+        let files = if ok { &self.files_succeeded } else { &self.files_failed };
+        files.fetch_add(1, Ordering::Relaxed);
+        let histogram = if long { &self.long } else { &self.normal };
+        histogram.lock().unwrap_or_else(|e| e.into_inner()).observe(elapsed.as_secs_f64());
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let counters = [
+            ("pretackler_requests_total", "HTTP 请求尝试次数（含重试）", &self.requests),
+            ("pretackler_retries_total", "重试次数", &self.retries),
+            ("pretackler_bytes_sent_total", "上行请求体字节数（含重试）", &self.bytes_sent),
+            ("pretackler_files_succeeded_total", "成功生成摘要的文件数", &self.files_succeeded),
+            ("pretackler_files_failed_total", "处理失败的文件数", &self.files_failed),
+        ];
+        for (name, help, value) in counters {
+            let _ = writeln!(out, "# HELP {} {}", name, help);
+            let _ = writeln!(out, "# TYPE {} counter", name);
+            let _ = writeln!(out, "{} {}", name, value.load(Ordering::Relaxed));
+        }
+
+        let name = "pretackler_file_duration_seconds";
+        let _ = writeln!(out, "# HELP {} 每文件处理耗时（按通道）", name);
+        let _ = writeln!(out, "# TYPE {} histogram", name);
+        for (channel, histogram) in [("normal", &self.normal), ("long", &self.long)] {
+            let h = histogram.lock().unwrap_or_else(|e| e.into_inner());
+            let mut cumulative = 0;
+            for (le, count) in DURATION_BUCKETS_SECS.iter().zip(h.counts) {
+                cumulative += count;
+                let _ = writeln!(out, "{}_bucket{{channel=\"{}\",le=\"{}\"}} {}", name, channel, le, cumulative);
+            }
+            let _ = writeln!(out, "{}_bucket{{channel=\"{}\",le=\"+Inf\"}} {}", name, channel, h.count);
+            let _ = writeln!(out, "{}_sum{{channel=\"{}\"}} {}", name, channel, h.sum_secs);
+            let _ = writeln!(out, "{}_count{{channel=\"{}\"}} {}", name, channel, h.count);
+        }
+        out
+    }
+}
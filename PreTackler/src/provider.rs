@@ -0,0 +1,531 @@
+use std::str::FromStr;
+
+use anyhow::{Context, Result, bail};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::Deserialize;
+use tracing::warn;
+
+// --provider：各厂商协议的差异（端点、鉴权头、请求体、流式事件与非流式响应的结构、密钥来源）集中在这里；
+// 重试、限速、超时、落盘等流程与协议无关，由 processor 统一处理
+
+pub const ANTHROPIC_API_BASE: &str = "https://api.anthropic.com";
+const CHAT_COMPLETIONS_PATH: &str = "/chat/completions";
+const ANTHROPIC_MESSAGES_PATH: &str = "/v1/messages";
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+// Messages API 要求 max_tokens，未设置 --max-tokens 时使用
+const ANTHROPIC_DEFAULT_MAX_TOKENS: u32 = 4096;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    #[default]
+    Deepseek, // DeepSeek 及其他 OpenAI 兼容端点（/chat/completions）
+    Anthropic, // Anthropic Messages API（/v1/messages）
+}
+
+impl FromStr for ProviderKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "deepseek" => Ok(Self::Deepseek),
+            "anthropic" => Ok(Self::Anthropic),
+            other => Err(format!("未知的 provider: {}（可选 deepseek、anthropic）", other)),
+        }
+    }
+}
+
+impl ProviderKind {
+    pub fn provider(self) -> &'static dyn Provider {
+        match self {
+            Self::Deepseek => &OpenAiCompatible,
+            Self::Anthropic => &Anthropic,
+        }
+    }
+}
+
+// 一次请求与协议无关的内容，由各 Provider 组装成自己的请求体
+pub struct ChatRequest<'a> {
+    pub model: &'a str,
+    pub stream: bool,
+    pub system: Vec<&'a str>, // 按顺序：主提示词、--context-file 背景资料
+    pub turns: Vec<(Role, &'a str)>, // few-shot 示例与实际 user 消息
+    pub max_tokens: Option<u32>,
+    pub temperature: f32,
+    pub top_k: u32,
+    pub top_p: Option<f64>,
+    pub frequency_penalty: Option<f64>,
+    pub presence_penalty: Option<f64>,
+    pub stop: &'a [String],
+    pub json_mode: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Role {
+    User,
+    Assistant,
+}
+
+impl Role {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::User => "user",
+            Self::Assistant => "assistant",
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct Usage {
+    #[serde(default)]
+    pub prompt_tokens: u64,
+    #[serde(default)]
+    pub completion_tokens: u64,
+    #[serde(default)]
+    pub prompt_cache_hit_tokens: u64, // DeepSeek 扩展字段，缺省视为 0
+}
+
+impl Usage {
+    pub fn accumulate(&mut self, other: &Usage) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.prompt_cache_hit_tokens += other.prompt_cache_hit_tokens.min(other.prompt_tokens);
+    }
+}
+
+// 一个流式事件解析出的片段，按出现顺序处理
+#[derive(Debug)]
+pub enum StreamPiece {
+    Reasoning(String),
+    Content(String),
+    Usage(Usage),
+    Done, // 正常结束（[DONE] / message_stop）
+}
+
+// 非流式响应
+#[derive(Debug, Default)]
+pub struct Completion {
+    pub reasoning: Option<String>,
+    pub content: String,
+    pub usage: Option<Usage>,
+}
+
+// 密钥来源的命名：显式文件变量、默认密钥文件名、多密钥与单密钥环境变量（前者逗号分隔）
+pub struct KeyNames {
+    pub file_var: &'static str,
+    pub file_name: &'static str,
+    pub env_vars: [&'static str; 2],
+}
+
+pub trait Provider: Send + Sync {
+    // 日志与错误信息中的厂商名
+    fn name(&self) -> &'static str;
+
+    fn key_names(&self) -> KeyNames;
+
+    // 用量是否按内置的 DeepSeek 单价估算费用
+    fn priced(&self) -> bool;
+
+    // 已知模型的上下文窗口（token），用于推算默认的请求体上限；未知模型返回 None
+    fn context_tokens(&self, model: &str) -> Option<u64>;
+
+    // 本协议不支持、发送时会被忽略的参数（启动时告警）
+    fn ignored_options(&self, req: &ChatRequest) -> Vec<&'static str>;
+
+    // 由 --api-base 得到完整端点，已带完整路径时原样使用
+    fn endpoint(&self, api_base: &str) -> String;
+
+    // 鉴权相关的请求头；密钥所在的值标记为敏感
+    fn auth_header(&self, api_key: &str) -> Result<HeaderMap>;
+
+    fn build_request_body(&self, req: &ChatRequest) -> serde_json::Value;
+
+    // 解析一个 SSE 事件的 data
+    fn parse_stream_line(&self, data: &str) -> Result<Vec<StreamPiece>>;
+
+    fn parse_completion(&self, body: &str) -> Result<Completion>;
+}
+
+fn sensitive(value: &str) -> Result<HeaderValue> {
+    let mut value = HeaderValue::from_str(value).context("密钥含有无法放入请求头的字符")?;
+    value.set_sensitive(true);
+    Ok(value)
+}
+
+// DeepSeek / OpenAI 兼容协议
+struct OpenAiCompatible;
+
+#[derive(Debug, Deserialize)]
+struct StreamResponse {
+    #[serde(default)]
+    choices: Vec<StreamChoice>,
+    usage: Option<Usage>, // 开启 stream_options.include_usage 后在最后一个 chunk 返回
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamChoice {
+    delta: Option<StreamDelta>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamDelta {
+    content: Option<String>,
+    reasoning_content: Option<String>, // deepseek-reasoner 的思维链，先于 content 输出
+}
+
+// --no-stream：常规 JSON 响应
+#[derive(Debug, Deserialize)]
+struct CompletionResponse {
+    choices: Vec<CompletionChoice>,
+    usage: Option<Usage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CompletionChoice {
+    message: Option<CompletionMessage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CompletionMessage {
+    content: Option<String>,
+    reasoning_content: Option<String>,
+}
+
+impl Provider for OpenAiCompatible {
+    fn name(&self) -> &'static str {
+        "DeepSeek"
+    }
+
+    fn key_names(&self) -> KeyNames {
+        KeyNames {
+            file_var: "DEEPSEEK_API_KEY_FILE",
+            file_name: "deepseek_api_key.secret",
+            env_vars: ["DEEPSEEK_API_KEYS", "DEEPSEEK_API_KEY"],
+        }
+    }
+
+    fn priced(&self) -> bool {
+        true
+    }
+
+    // deepseek-chat / deepseek-reasoner 均为 128K；其他 OpenAI 兼容端点的模型无从得知
+    fn context_tokens(&self, model: &str) -> Option<u64> {
+        model.starts_with("deepseek-").then_some(128 * 1024)
+    }
+
+    fn ignored_options(&self, _req: &ChatRequest) -> Vec<&'static str> {
+        Vec::new()
+    }
+
+    fn endpoint(&self, api_base: &str) -> String {
+        let base = api_base.trim().trim_end_matches('/');
+        if base.ends_with(CHAT_COMPLETIONS_PATH) {
+            base.to_string()
+        } else {
+            format!("{}{}", base, CHAT_COMPLETIONS_PATH)
+        }
+    }
+
+    fn auth_header(&self, api_key: &str) -> Result<HeaderMap> {
+        let mut headers = HeaderMap::new();
+        headers.insert(reqwest::header::AUTHORIZATION, sensitive(&format!("Bearer {}", api_key))?);
+        Ok(headers)
+    }
+
+    fn build_request_body(&self, req: &ChatRequest) -> serde_json::Value {
+        let mut body = serde_json::json!({
+            "model": req.model,
+            "stream": req.stream,
+            "temperature": req.temperature,
+            "top_k": req.top_k,
+        });
+        let mut messages: Vec<serde_json::Value> = req
+            .system
+            .iter()
+            .map(|s| serde_json::json!({"role": "system", "content": s}))
+            .collect();
+        for (role, content) in &req.turns {
+            messages.push(serde_json::json!({"role": role.as_str(), "content": content}));
+        }
+        body["messages"] = serde_json::Value::Array(messages);
+        // 流式模式下请求在末尾附带 usage，用于统计 token 与费用
+        if req.stream {
+            body["stream_options"] = serde_json::json!({"include_usage": true});
+        }
+        // 均未设置时不写入该键
+        if let Some(v) = req.max_tokens {
+            body["max_tokens"] = v.into();
+        }
+        if req.json_mode {
+            body["response_format"] = serde_json::json!({"type": "json_object"});
+        }
+        if !req.stop.is_empty() {
+            body["stop"] = serde_json::json!(req.stop);
+        }
+        // 以下采样参数仅在用户显式提供时写入，避免把默认值强加给不支持的模型
+        if let Some(v) = req.top_p {
+            body["top_p"] = v.into();
+        }
+        if let Some(v) = req.frequency_penalty {
+            body["frequency_penalty"] = v.into();
+        }
+        if let Some(v) = req.presence_penalty {
+            body["presence_penalty"] = v.into();
+        }
+        body
+    }
+
+    fn parse_stream_line(&self, data: &str) -> Result<Vec<StreamPiece>> {
+        let payload = data.trim();
+        if payload.is_empty() {
+            return Ok(Vec::new());
+        }
+        if payload == "[DONE]" {
+            return Ok(vec![StreamPiece::Done]);
+        }
+        let parsed: StreamResponse = match serde_json::from_str(payload) {
+            Ok(resp) => resp,
+            Err(err) => {
+                warn!("解析流式响应失败: {}", err);
+                return Ok(Vec::new());
+            }
+        };
+        let mut pieces = Vec::new();
+        if let Some(u) = parsed.usage {
+            pieces.push(StreamPiece::Usage(u));
+        }
+        for delta in parsed.choices.into_iter().filter_map(|c| c.delta) {
+            if let Some(reasoning) = delta.reasoning_content {
+                pieces.push(StreamPiece::Reasoning(reasoning));
+            }
+            if let Some(content) = delta.content {
+                pieces.push(StreamPiece::Content(content));
+            }
+        }
+        Ok(pieces)
+    }
+
+    fn parse_completion(&self, body: &str) -> Result<Completion> {
+        let parsed: CompletionResponse = serde_json::from_str(body)
+            .with_context(|| format!("解析 DeepSeek 响应失败: {}", body))?;
+        let message = parsed.choices.into_iter().next().and_then(|c| c.message);
+        Ok(Completion {
+            reasoning: message.as_ref().and_then(|m| m.reasoning_content.clone()),
+            content: message.and_then(|m| m.content).unwrap_or_default(),
+            usage: parsed.usage,
+        })
+    }
+}
+
+// Anthropic Messages API：system 为顶层字段，鉴权用 x-api-key，流式为带 type 的命名事件
+struct Anthropic;
+
+#[derive(Debug, Default, Deserialize)]
+struct AnthropicUsage {
+    #[serde(default)]
+    input_tokens: u64, // 不含缓存读写的部分
+    #[serde(default)]
+    output_tokens: u64,
+    #[serde(default)]
+    cache_read_input_tokens: u64,
+    #[serde(default)]
+    cache_creation_input_tokens: u64,
+}
+
+impl AnthropicUsage {
+    // 折算为 OpenAI 口径：输入总数含缓存部分，缓存命中为其中读缓存的部分
+    fn into_usage(self) -> Usage {
+        Usage {
+            prompt_tokens: self.input_tokens + self.cache_read_input_tokens + self.cache_creation_input_tokens,
+            completion_tokens: self.output_tokens,
+            prompt_cache_hit_tokens: self.cache_read_input_tokens,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum AnthropicEvent {
+    MessageStart { message: AnthropicMessageStart },
+    ContentBlockDelta { delta: AnthropicDelta },
+    MessageDelta {
+        #[serde(default)]
+        usage: Option<AnthropicUsage>,
+    },
+    MessageStop,
+    Error { error: AnthropicError },
+    // ping、content_block_start/stop 等
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicMessageStart {
+    #[serde(default)]
+    usage: Option<AnthropicUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum AnthropicDelta {
+    TextDelta { text: String },
+    ThinkingDelta { thinking: String },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicError {
+    #[serde(rename = "type", default)]
+    kind: String,
+    #[serde(default)]
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct AnthropicResponse {
+    #[serde(default)]
+    content: Vec<AnthropicBlock>,
+    usage: Option<AnthropicUsage>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum AnthropicBlock {
+    Text { text: String },
+    Thinking { thinking: String },
+    #[serde(other)]
+    Other,
+}
+
+impl Provider for Anthropic {
+    fn name(&self) -> &'static str {
+        "Anthropic"
+    }
+
+    fn key_names(&self) -> KeyNames {
+        KeyNames {
+            file_var: "ANTHROPIC_API_KEY_FILE",
+            file_name: "anthropic_api_key.secret",
+            env_vars: ["ANTHROPIC_API_KEYS", "ANTHROPIC_API_KEY"],
+        }
+    }
+
+    fn priced(&self) -> bool {
+        false
+    }
+
+    // 当前 Claude 模型的标准上下文均为 200K
+    fn context_tokens(&self, model: &str) -> Option<u64> {
+        model.starts_with("claude-").then_some(200_000)
+    }
+
+    fn ignored_options(&self, req: &ChatRequest) -> Vec<&'static str> {
+        let mut ignored = Vec::new();
+        if req.frequency_penalty.is_some() {
+            ignored.push("--frequency-penalty");
+        }
+        if req.presence_penalty.is_some() {
+            ignored.push("--presence-penalty");
+        }
+        if req.json_mode {
+            ignored.push("--json-mode");
+        }
+        ignored
+    }
+
+    fn endpoint(&self, api_base: &str) -> String {
+        let base = api_base.trim().trim_end_matches('/');
+        if base.ends_with(ANTHROPIC_MESSAGES_PATH) {
+            return base.to_string();
+        }
+        // 兼容写到 /v1 为止的 base URL
+        format!("{}{}", base.strip_suffix("/v1").unwrap_or(base), ANTHROPIC_MESSAGES_PATH)
+    }
+
+    fn auth_header(&self, api_key: &str) -> Result<HeaderMap> {
+        let mut headers = HeaderMap::new();
+        headers.insert(HeaderName::from_static("x-api-key"), sensitive(api_key)?);
+        headers.insert(HeaderName::from_static("anthropic-version"), HeaderValue::from_static(ANTHROPIC_VERSION));
+        Ok(headers)
+    }
+
+    fn build_request_body(&self, req: &ChatRequest) -> serde_json::Value {
+        let messages: Vec<serde_json::Value> = req
+            .turns
+            .iter()
+            .map(|(role, content)| serde_json::json!({"role": role.as_str(), "content": content}))
+            .collect();
+        let mut body = serde_json::json!({
+            "model": req.model,
+            "max_tokens": req.max_tokens.unwrap_or(ANTHROPIC_DEFAULT_MAX_TOKENS),
+            "messages": messages,
+            "stream": req.stream,
+            "temperature": req.temperature,
+            "top_k": req.top_k,
+        });
+        // 多段 system（提示词与背景资料）空一行拼接
+        if !req.system.is_empty() {
+            body["system"] = req.system.join("\n\n").into();
+        }
+        if !req.stop.is_empty() {
+            body["stop_sequences"] = serde_json::json!(req.stop);
+        }
+        if let Some(v) = req.top_p {
+            body["top_p"] = v.into();
+        }
+        body
+    }
+
+    fn parse_stream_line(&self, data: &str) -> Result<Vec<StreamPiece>> {
+        let payload = data.trim();
+        if payload.is_empty() {
+            return Ok(Vec::new());
+        }
+        let event: AnthropicEvent = match serde_json::from_str(payload) {
+            Ok(event) => event,
+            Err(err) => {
+                warn!("解析流式响应失败: {}", err);
+                return Ok(Vec::new());
+            }
+        };
+        Ok(match event {
+            // message_start 给出输入 token，message_delta 给出累计的输出 token；
+            // 前者的 output_tokens 只是占位，计入会与后者重复
+            AnthropicEvent::MessageStart { message } => message
+                .usage
+                .map(|u| StreamPiece::Usage(Usage { completion_tokens: 0, ..u.into_usage() }))
+                .into_iter()
+                .collect(),
+            AnthropicEvent::MessageDelta { usage } => usage
+                .map(|u| StreamPiece::Usage(Usage { completion_tokens: u.output_tokens, ..Default::default() }))
+                .into_iter()
+                .collect(),
+            AnthropicEvent::ContentBlockDelta { delta: AnthropicDelta::TextDelta { text } } => {
+                vec![StreamPiece::Content(text)]
+            }
+            AnthropicEvent::ContentBlockDelta { delta: AnthropicDelta::ThinkingDelta { thinking } } => {
+                vec![StreamPiece::Reasoning(thinking)]
+            }
+            AnthropicEvent::MessageStop => vec![StreamPiece::Done],
+            AnthropicEvent::Error { error } => bail!("Anthropic 流式响应返回错误: {}（type={}）", error.message, error.kind),
+            AnthropicEvent::ContentBlockDelta { delta: AnthropicDelta::Other } | AnthropicEvent::Other => Vec::new(),
+        })
+    }
+
+    fn parse_completion(&self, body: &str) -> Result<Completion> {
+        let parsed: AnthropicResponse = serde_json::from_str(body)
+            .with_context(|| format!("解析 Anthropic 响应失败: {}", body))?;
+        let mut completion = Completion { usage: parsed.usage.map(AnthropicUsage::into_usage), ..Default::default() };
+        let mut reasoning = String::new();
+        for block in parsed.content {
+            match block {
+                AnthropicBlock::Text { text } => completion.content.push_str(&text),
+                AnthropicBlock::Thinking { thinking } => reasoning.push_str(&thinking),
+                AnthropicBlock::Other => {}
+            }
+        }
+        completion.reasoning = (!reasoning.is_empty()).then_some(reasoning);
+        Ok(completion)
+    }
+}
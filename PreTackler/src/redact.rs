@@ -0,0 +1,91 @@
+use std::borrow::Cow;
+
+use anyhow::{Context, Result};
+use regex_automata::meta::Regex;
+
+// --redact：发送前把疑似密钥的内容替换为占位符。按字节匹配，文本、旧编码与 base64 路径都在编码前处理。
+// 模式含捕获组时只替换第 1 组（保留 `password = ` 这类上下文），否则替换整个匹配
+
+const REDACTED: &[u8] = b"***REDACTED***";
+
+// 内置模式：(名称, 正则)。名称只用于 verbose 统计
+const BUILTIN_PATTERNS: &[(&str, &str)] = &[
+    ("private-key", r"-----BEGIN [A-Z ]*PRIVATE KEY-----[\s\S]*?-----END [A-Z ]*PRIVATE KEY-----"),
+    ("aws-access-key", r"\b(?:AKIA|ASIA)[0-9A-Z]{16}\b"),
+    ("github-token", r"\b(?:gh[pousr]_[A-Za-z0-9]{36,}|github_pat_[A-Za-z0-9_]{50,})"),
+    ("slack-token", r"\bxox[abposr]-[A-Za-z0-9-]{10,}"),
+    ("google-api-key", r"\bAIza[0-9A-Za-z_-]{35}"),
+    ("sk-key", r"\bsk-[A-Za-z0-9_-]{20,}"),
+    ("jwt", r"\beyJ[A-Za-z0-9_-]{10,}\.[A-Za-z0-9_-]{10,}\.[A-Za-z0-9_-]{10,}"),
+    (
+        "secret-assignment",
+        r#"(?i)(?:password|passwd|secret|token|api[_-]?key|access[_-]?key)["']?\s*[:=]\s*["']?([^\s"',;]{8,})"#,
+    ),
+];
+
+pub struct Redactor {
+    rules: Vec<(String, Regex)>,
+}
+
+// 单个文件的脱敏结果统计：(规则名, 次数)，只含命中过的规则
+#[derive(Debug, Default)]
+pub struct RedactStats {
+    pub hits: Vec<(String, usize)>,
+}
+
+impl RedactStats {
+    pub fn total(&self) -> usize {
+        self.hits.iter().map(|(_, n)| n).sum()
+    }
+}
+
+impl std::fmt::Display for RedactStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let parts = self.hits.iter().map(|(name, n)| format!("{}×{}", name, n)).collect::<Vec<_>>();
+        write!(f, "{}", parts.join("，"))
+    }
+}
+
+impl Redactor {
+    // 内置模式在前，用户模式按 --redact-pattern 的顺序追加（名称为 custom#序号）
+    pub fn new(user_patterns: &[String]) -> Result<Self> {
+        let mut rules = Vec::new();
+        for (name, pattern) in BUILTIN_PATTERNS {
+            let re = Regex::new(pattern).with_context(|| format!("内置脱敏规则 {} 编译失败", name))?;
+            rules.push((name.to_string(), re));
+        }
+        for (idx, pattern) in user_patterns.iter().enumerate() {
+            let re = Regex::new(pattern).with_context(|| format!("--redact-pattern 不是有效的正则: {}", pattern))?;
+            rules.push((format!("custom#{}", idx + 1), re));
+        }
+        Ok(Redactor { rules })
+    }
+
+    // 依次应用各规则（后一条作用于前一条的结果）；没有命中时不复制
+    pub fn redact<'a>(&self, bytes: &'a [u8]) -> (Cow<'a, [u8]>, RedactStats) {
+        let mut current: Cow<'a, [u8]> = Cow::Borrowed(bytes);
+        let mut stats = RedactStats::default();
+        for (name, re) in &self.rules {
+            let mut spans = Vec::new();
+            for caps in re.captures_iter(current.as_ref()) {
+                if let Some(span) = caps.get_group(1).or_else(|| caps.get_match().map(|m| m.span())) {
+                    spans.push(span);
+                }
+            }
+            if spans.is_empty() {
+                continue;
+            }
+            let mut out = Vec::with_capacity(current.len());
+            let mut last = 0;
+            for span in &spans {
+                out.extend_from_slice(&current[last..span.start]);
+                out.extend_from_slice(REDACTED);
+                last = span.end;
+            }
+            out.extend_from_slice(&current[last..]);
+            stats.hits.push((name.clone(), spans.len()));
+            current = Cow::Owned(out);
+        }
+        (current, stats)
+    }
+}
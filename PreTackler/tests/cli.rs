@@ -0,0 +1,174 @@
+// 端到端测试：以子进程运行 pretackler，请求发往进程内的 mock 端点，不访问网络
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+// OpenAI 兼容的流式响应：一段正文后 [DONE]，Connection: close 以连接关闭作为响应体结束
+const SSE_RESPONSE: &str = "HTTP/1.1 200 OK\r\ncontent-type: text/event-stream\r\nconnection: close\r\n\r\n\
+    data: {\"choices\":[{\"delta\":{\"content\":\"摘要正文\\n\"}}]}\n\n\
+    data: [DONE]\n\n";
+
+struct MockServer {
+    base: String,
+    requests: Arc<AtomicUsize>,
+}
+
+impl MockServer {
+    fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&requests);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else { continue };
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut content_length = 0usize;
+                loop {
+                    let mut line = String::new();
+                    if reader.read_line(&mut line).unwrap_or(0) == 0 || line == "\r\n" {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(':')
+                        && name.eq_ignore_ascii_case("content-length")
+                    {
+                        content_length = value.trim().parse().unwrap_or(0);
+                    }
+                }
+                let mut body = vec![0u8; content_length];
+                if reader.read_exact(&mut body).is_err() {
+                    continue;
+                }
+                counter.fetch_add(1, Ordering::SeqCst);
+                let _ = stream.write_all(SSE_RESPONSE.as_bytes());
+            }
+        });
+        Self { base, requests }
+    }
+
+    fn requests(&self) -> usize {
+        self.requests.load(Ordering::SeqCst)
+    }
+}
+
+// 每个测试独占一个临时目录，进程号区分并发运行的测试进程
+fn scratch_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("pretackler-cli-{}-{}", std::process::id(), name));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn write_file(path: &Path, content: &str) {
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    std::fs::write(path, content).unwrap();
+}
+
+fn run_pretackler(cwd: &Path, server: &MockServer, args: &[&str]) -> Output {
+    let prompt = Path::new(env!("CARGO_MANIFEST_DIR")).join("prompt_template.md");
+    let output = Command::new(env!("CARGO_BIN_EXE_pretackler"))
+        .current_dir(cwd)
+        .env("DEEPSEEK_API_KEY", "test-key")
+        .env_remove("DEEPSEEK_API_BASE")
+        .args(args)
+        .arg("--prompt")
+        .arg(&prompt)
+        .args(["--api-base", &server.base, "--no-bandwidth-probe"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "pretackler 异常退出: {}", String::from_utf8_lossy(&output.stderr));
+    output
+}
+
+fn manifest_entries(output_root: &Path) -> Vec<serde_json::Value> {
+    let manifest: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(output_root.join("manifest.json")).unwrap()).unwrap();
+    manifest["entries"].as_array().unwrap().clone()
+}
+
+// 响应已完整收到后摘要落盘失败：该文件直接记为失败，不回到重试循环再发一次请求
+#[test]
+fn commit_failure_after_full_response_is_not_retried() {
+    let root = scratch_dir("commit-failure");
+    write_file(&root.join("src/x.rs"), "fn x() {}\n");
+    // 摘要路径上预先放一个非空目录，重命名必然失败（root 下同样有效，不依赖只读权限）
+    write_file(&root.join("src.summaries.v1/x.rs.summary.v1.md/keep"), "");
+
+    let server = MockServer::start();
+    let output = run_pretackler(&root, &server, &["src"]);
+
+    assert_eq!(server.requests(), 1, "落盘失败后不应重发请求");
+    let entries = manifest_entries(&root.join("src.summaries.v1"));
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0]["rel_path"], "x.rs");
+    assert_eq!(entries[0]["status"], "failed");
+    assert!(entries[0]["error"].as_str().unwrap().contains("摘要落盘失败"), "{}", entries[0]["error"]);
+    assert!(String::from_utf8_lossy(&output.stdout).contains("失败 1 个"));
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+// 目录计数：输出根目录下镜像创建的子目录数（不含根目录本身），空目录计入，被忽略的目录不计；
+// 边遍历边分派与完整遍历（非 random 的 --order）两条路径结果一致
+#[test]
+fn directories_processed_counts_mirrored_subdirs() {
+    let root = scratch_dir("directories");
+    write_file(&root.join("src/top.rs"), "fn top() {}\n");
+    write_file(&root.join("src/a/x.rs"), "fn x() {}\n");
+    write_file(&root.join("src/a/b/y.rs"), "fn y() {}\n");
+    std::fs::create_dir_all(root.join("src/c")).unwrap();
+    write_file(&root.join("src/ignored/z.rs"), "fn z() {}\n");
+    write_file(&root.join("src/ignored/deep/w.rs"), "fn w() {}\n");
+    write_file(&root.join("src/.pretacklerignore"), "ignored/\n");
+
+    let server = MockServer::start();
+    for extra in [&[][..], &["--order", "path"][..]] {
+        let output_root = root.join("src.summaries.v1");
+        let _ = std::fs::remove_dir_all(&output_root);
+        let mut args = vec!["src"];
+        args.extend_from_slice(extra);
+        let output = run_pretackler(&root, &server, &args);
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        // 文件：top.rs、a/x.rs、a/b/y.rs 与 .pretacklerignore 本身；目录：a、a/b、c
+        assert!(stdout.contains("文件 4 个，目录 3 个，失败 0 个"), "{:?}: {}", extra, stdout);
+        assert!(output_root.join("c").is_dir());
+        assert!(!output_root.join("ignored").exists());
+    }
+    assert_eq!(server.requests(), 8);
+    let _ = std::fs::remove_dir_all(&root);
+}
+
+// --retry-failed 只重跑失败清单中的文件，清单之外的源文件不代表已删除，--hash-prune 不得清理它们
+#[test]
+fn hash_prune_with_retry_failed_keeps_other_summaries() {
+    let root = scratch_dir("retry-failed-prune");
+    write_file(&root.join("src/ok.rs"), "fn ok() {}\n");
+    write_file(&root.join("src/a/other.rs"), "fn other() {}\n");
+    write_file(&root.join("src/bad.rs"), "fn bad() {}\n");
+    let output_root = root.join("src.summaries.v1");
+    // 第一次运行让 bad.rs 落盘失败，进入 failed.txt
+    let blocker = output_root.join("bad.rs.summary.v1.md");
+    write_file(&blocker.join("keep"), "");
+
+    let server = MockServer::start();
+    run_pretackler(&root, &server, &["src", "--hash-skip"]);
+    assert!(std::fs::read_to_string(output_root.join("failed.txt")).unwrap().contains("bad.rs"));
+
+    std::fs::remove_dir_all(&blocker).unwrap();
+    run_pretackler(&root, &server, &["src", "--retry-failed", "src.summaries.v1/failed.txt", "--hash-skip", "--hash-prune"]);
+
+    for summary in ["ok.rs.summary.v1.md", "a/other.rs.summary.v1.md", "bad.rs.summary.v1.md"] {
+        assert!(output_root.join(summary).is_file(), "{} 不应被清理", summary);
+    }
+    let hashes: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(output_root.join(".hashes.json")).unwrap()).unwrap();
+    for rel in ["ok.rs", "a/other.rs", "bad.rs"] {
+        assert!(hashes.get(rel).is_some(), "{} 的哈希记录不应被清理: {}", rel, hashes);
+    }
+    let _ = std::fs::remove_dir_all(&root);
+}